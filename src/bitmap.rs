@@ -0,0 +1,343 @@
+//! A compressed bitmap over `u32` ordinals, following the roaring
+//! bitmap layout: the 32-bit space is partitioned into 16-bit-keyed
+//! containers, and each container is either a sorted array (cheap for
+//! sparse data) or a fixed-size bitmap (cheap for dense data).
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Containers denser than this many entries are stored as a bitmap
+/// instead of a sorted array.
+const ARRAY_MAX: usize = 4096;
+/// 1024 64-bit words cover the full 16-bit low range (65536 bits).
+const BITMAP_WORDS: usize = 1024;
+
+#[derive(Debug, Clone)]
+enum Container {
+    Array(Vec<u16>),
+    Bitmap(Box<[u64; BITMAP_WORDS]>),
+}
+
+impl Container {
+    fn new_array() -> Self {
+        Container::Array(Vec::new())
+    }
+
+    fn from_sorted(values: Vec<u16>) -> Self {
+        if values.len() <= ARRAY_MAX {
+            Container::Array(values)
+        } else {
+            let mut words = Box::new([0u64; BITMAP_WORDS]);
+            for v in values {
+                words[v as usize / 64] |= 1u64 << (v as usize % 64);
+            }
+            Container::Bitmap(words)
+        }
+    }
+
+    fn insert(&mut self, low: u16) -> bool {
+        let should_promote = match self {
+            Container::Array(values) => match values.binary_search(&low) {
+                Ok(_) => return false,
+                Err(pos) => {
+                    values.insert(pos, low);
+                    values.len() > ARRAY_MAX
+                }
+            },
+            Container::Bitmap(words) => {
+                let (word, bit) = (low as usize / 64, low as usize % 64);
+                let mask = 1u64 << bit;
+                let was_set = words[word] & mask != 0;
+                words[word] |= mask;
+                return !was_set;
+            }
+        };
+        if should_promote {
+            self.promote_to_bitmap();
+        }
+        true
+    }
+
+    fn promote_to_bitmap(&mut self) {
+        if let Container::Array(values) = self {
+            let mut words = Box::new([0u64; BITMAP_WORDS]);
+            for &v in values.iter() {
+                words[v as usize / 64] |= 1u64 << (v as usize % 64);
+            }
+            *self = Container::Bitmap(words);
+        }
+    }
+
+    fn remove(&mut self, low: u16) -> bool {
+        match self {
+            Container::Array(values) => match values.binary_search(&low) {
+                Ok(pos) => {
+                    values.remove(pos);
+                    true
+                }
+                Err(_) => false,
+            },
+            Container::Bitmap(words) => {
+                let (word, bit) = (low as usize / 64, low as usize % 64);
+                let mask = 1u64 << bit;
+                let was_set = words[word] & mask != 0;
+                words[word] &= !mask;
+                was_set
+            }
+        }
+    }
+
+    fn contains(&self, low: u16) -> bool {
+        match self {
+            Container::Array(values) => values.binary_search(&low).is_ok(),
+            Container::Bitmap(words) => {
+                let (word, bit) = (low as usize / 64, low as usize % 64);
+                words[word] & (1u64 << bit) != 0
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Container::Array(values) => values.len(),
+            Container::Bitmap(words) => words.iter().map(|w| w.count_ones() as usize).sum(),
+        }
+    }
+
+    fn iter(&self) -> Vec<u16> {
+        match self {
+            Container::Array(values) => values.clone(),
+            Container::Bitmap(words) => {
+                let mut out = Vec::new();
+                for (i, word) in words.iter().enumerate() {
+                    let mut remaining = *word;
+                    while remaining != 0 {
+                        let bit = remaining.trailing_zeros();
+                        out.push((i * 64 + bit as usize) as u16);
+                        remaining &= remaining - 1;
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    fn and(&self, other: &Container) -> Container {
+        let (a, b) = (self.iter(), other.iter());
+        let (mut i, mut j) = (0, 0);
+        let mut result = Vec::new();
+        while i < a.len() && j < b.len() {
+            match a[i].cmp(&b[j]) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    result.push(a[i]);
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        Container::from_sorted(result)
+    }
+
+    fn or(&self, other: &Container) -> Container {
+        let (a, b) = (self.iter(), other.iter());
+        let (mut i, mut j) = (0, 0);
+        let mut result = Vec::with_capacity(a.len() + b.len());
+        while i < a.len() && j < b.len() {
+            match a[i].cmp(&b[j]) {
+                Ordering::Less => {
+                    result.push(a[i]);
+                    i += 1;
+                }
+                Ordering::Greater => {
+                    result.push(b[j]);
+                    j += 1;
+                }
+                Ordering::Equal => {
+                    result.push(a[i]);
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        result.extend_from_slice(&a[i..]);
+        result.extend_from_slice(&b[j..]);
+        Container::from_sorted(result)
+    }
+
+    fn andnot(&self, other: &Container) -> Container {
+        let (a, b) = (self.iter(), other.iter());
+        let (mut i, mut j) = (0, 0);
+        let mut result = Vec::new();
+        while i < a.len() {
+            if j < b.len() && a[i] == b[j] {
+                i += 1;
+                j += 1;
+            } else if j < b.len() && a[i] > b[j] {
+                j += 1;
+            } else {
+                result.push(a[i]);
+                i += 1;
+            }
+        }
+        Container::from_sorted(result)
+    }
+}
+
+/// A compressed, sorted set of `u32` ordinals used as posting lists for
+/// secondary indexes.
+#[derive(Debug, Clone, Default)]
+pub struct RoaringBitmap {
+    containers: BTreeMap<u16, Container>,
+}
+
+impl RoaringBitmap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn split(value: u32) -> (u16, u16) {
+        ((value >> 16) as u16, (value & 0xFFFF) as u16)
+    }
+
+    pub fn insert(&mut self, value: u32) -> bool {
+        let (high, low) = Self::split(value);
+        self.containers
+            .entry(high)
+            .or_insert_with(Container::new_array)
+            .insert(low)
+    }
+
+    pub fn remove(&mut self, value: u32) -> bool {
+        let (high, low) = Self::split(value);
+        match self.containers.get_mut(&high) {
+            Some(container) => {
+                let removed = container.remove(low);
+                if container.len() == 0 {
+                    self.containers.remove(&high);
+                }
+                removed
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        let (high, low) = Self::split(value);
+        self.containers
+            .get(&high)
+            .is_some_and(|container| container.contains(low))
+    }
+
+    pub fn len(&self) -> usize {
+        self.containers.values().map(Container::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.containers.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.containers.iter().flat_map(|(&high, container)| {
+            container
+                .iter()
+                .into_iter()
+                .map(move |low| ((high as u32) << 16) | low as u32)
+        })
+    }
+
+    pub fn and(&self, other: &RoaringBitmap) -> RoaringBitmap {
+        let mut result = RoaringBitmap::new();
+        for (key, container) in &self.containers {
+            if let Some(other_container) = other.containers.get(key) {
+                let merged = container.and(other_container);
+                if merged.len() > 0 {
+                    result.containers.insert(*key, merged);
+                }
+            }
+        }
+        result
+    }
+
+    pub fn or(&self, other: &RoaringBitmap) -> RoaringBitmap {
+        let mut result = self.clone();
+        result.or_inplace(other);
+        result
+    }
+
+    pub fn or_inplace(&mut self, other: &RoaringBitmap) {
+        for (key, container) in &other.containers {
+            self.containers
+                .entry(*key)
+                .and_modify(|existing| *existing = existing.or(container))
+                .or_insert_with(|| container.clone());
+        }
+    }
+
+    pub fn andnot(&self, other: &RoaringBitmap) -> RoaringBitmap {
+        let mut result = RoaringBitmap::new();
+        for (key, container) in &self.containers {
+            let merged = match other.containers.get(key) {
+                Some(other_container) => container.andnot(other_container),
+                None => container.clone(),
+            };
+            if merged.len() > 0 {
+                result.containers.insert(*key, merged);
+            }
+        }
+        result
+    }
+}
+
+impl FromIterator<u32> for RoaringBitmap {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut bitmap = RoaringBitmap::new();
+        for value in iter {
+            bitmap.insert(value);
+        }
+        bitmap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_contains_remove_roundtrip() {
+        let mut bitmap = RoaringBitmap::new();
+        assert!(bitmap.insert(5));
+        assert!(!bitmap.insert(5));
+        assert!(bitmap.contains(5));
+        assert!(bitmap.remove(5));
+        assert!(!bitmap.contains(5));
+    }
+
+    #[test]
+    fn promotes_to_bitmap_container_past_the_array_threshold() {
+        let mut bitmap: RoaringBitmap = (0..=ARRAY_MAX as u32).collect();
+        assert_eq!(bitmap.len(), ARRAY_MAX + 1);
+        assert!(matches!(bitmap.containers.get(&0), Some(Container::Bitmap(_))));
+        for v in 0..=ARRAY_MAX as u32 {
+            assert!(bitmap.contains(v));
+        }
+        bitmap.remove(0);
+        assert!(!bitmap.contains(0));
+    }
+
+    #[test]
+    fn and_or_andnot_merge_containers_correctly() {
+        let a: RoaringBitmap = [1, 2, 3, 70_000].into_iter().collect();
+        let b: RoaringBitmap = [2, 3, 4, 70_000].into_iter().collect();
+
+        let and: Vec<u32> = a.and(&b).iter().collect();
+        assert_eq!(and, vec![2, 3, 70_000]);
+
+        let or: Vec<u32> = a.or(&b).iter().collect();
+        assert_eq!(or, vec![1, 2, 3, 4, 70_000]);
+
+        let andnot: Vec<u32> = a.andnot(&b).iter().collect();
+        assert_eq!(andnot, vec![1]);
+    }
+}
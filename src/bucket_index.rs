@@ -0,0 +1,392 @@
+//! On-disk extendible hash bucket map for the document-id index.
+//!
+//! Document ids hash into one of `2^k` fixed-size buckets; each bucket
+//! stores `(id, first_block)` pairs, scanned on lookup. The hash only
+//! picks the bucket — entries are disambiguated by the literal id, so
+//! two ids landing in the same bucket (expected; that's what buckets
+//! are for) never get confused with each other, even on a hash
+//! collision. When a bucket's entry count would exceed the configured
+//! max probe length, the directory doubles (`k += 1`) and every entry
+//! is redistributed across the new bucket count, so lookups stay
+//! roughly O(1) without ever holding the whole key set resident in
+//! RAM. A small LRU keeps hot buckets cached; the rest are read from
+//! disk on demand.
+
+use std::collections::{hash_map::DefaultHasher, HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const HEADER_SIZE: u64 = 16; // u64 k + u64 max_probe_len
+
+/// Budgeted bytes for one `(id, first_block)` entry inside a bucket's
+/// fixed-size region. A full bucket fits id strings of up to 80 bytes
+/// each (e.g. a `Uuid::to_string()`, which is 36); writing a bucket
+/// whose entries overflow its region fails with an explicit error
+/// rather than silently truncating.
+const ENTRY_BUDGET: u64 = 96;
+
+/// Tuning knobs for [`BucketIndex`], exposed so callers can trade
+/// memory/IO for lookup latency.
+#[derive(Debug, Clone, Copy)]
+pub struct BucketIndexConfig {
+    /// The directory starts with `2^initial_k` buckets.
+    pub initial_k: u32,
+    /// A bucket doubles the directory once inserting into it would
+    /// make it hold more than this many entries.
+    pub max_probe_len: usize,
+    /// Number of buckets kept resident in the hot-bucket cache.
+    pub cache_size: usize,
+}
+
+impl Default for BucketIndexConfig {
+    fn default() -> Self {
+        BucketIndexConfig {
+            initial_k: 4, // 16 buckets
+            max_probe_len: 8,
+            cache_size: 64,
+        }
+    }
+}
+
+struct BucketCache {
+    capacity: usize,
+    buckets: HashMap<u64, Vec<(String, u64)>>,
+    lru: VecDeque<u64>,
+}
+
+impl BucketCache {
+    fn new(capacity: usize) -> Self {
+        BucketCache {
+            capacity,
+            buckets: HashMap::new(),
+            lru: VecDeque::new(),
+        }
+    }
+
+    fn get(&mut self, bucket_id: u64) -> Option<&Vec<(String, u64)>> {
+        if let Some(pos) = self.lru.iter().position(|&id| id == bucket_id) {
+            self.lru.remove(pos);
+            self.lru.push_front(bucket_id);
+        }
+        self.buckets.get(&bucket_id)
+    }
+
+    fn insert(&mut self, bucket_id: u64, entries: Vec<(String, u64)>) {
+        if !self.buckets.contains_key(&bucket_id) && self.buckets.len() >= self.capacity {
+            if let Some(evicted) = self.lru.pop_back() {
+                self.buckets.remove(&evicted);
+            }
+        }
+        self.buckets.insert(bucket_id, entries);
+        self.lru.push_front(bucket_id);
+    }
+
+    fn clear(&mut self) {
+        self.buckets.clear();
+        self.lru.clear();
+    }
+}
+
+pub struct BucketIndex {
+    file: File,
+    k: u32,
+    max_probe_len: usize,
+    cache: BucketCache,
+}
+
+impl BucketIndex {
+    pub fn open<P: AsRef<Path>>(path: P, config: BucketIndexConfig) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(path)?;
+
+        let existing_len = file.metadata()?.len();
+        let (k, max_probe_len) = if existing_len >= HEADER_SIZE {
+            let mut header = [0u8; HEADER_SIZE as usize];
+            file.seek(SeekFrom::Start(0))?;
+            file.read_exact(&mut header)?;
+            let k = u64::from_le_bytes(header[0..8].try_into().unwrap()) as u32;
+            let max_probe_len = u64::from_le_bytes(header[8..16].try_into().unwrap()) as usize;
+            (k, max_probe_len)
+        } else {
+            (config.initial_k, config.max_probe_len)
+        };
+
+        let mut index = BucketIndex {
+            file,
+            k,
+            max_probe_len,
+            cache: BucketCache::new(config.cache_size),
+        };
+
+        let required_len = HEADER_SIZE + (1u64 << index.k) * index.region_size();
+        if existing_len < required_len {
+            index.file.set_len(required_len)?;
+            index.write_header()?;
+        }
+
+        Ok(index)
+    }
+
+    fn region_size(&self) -> u64 {
+        8 + self.max_probe_len as u64 * ENTRY_BUDGET
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let mut header = [0u8; HEADER_SIZE as usize];
+        header[0..8].copy_from_slice(&(self.k as u64).to_le_bytes());
+        header[8..16].copy_from_slice(&(self.max_probe_len as u64).to_le_bytes());
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&header)
+    }
+
+    fn hash_id(id: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn bucket_id(&self, hash: u64) -> u64 {
+        hash & ((1u64 << self.k) - 1)
+    }
+
+    fn read_bucket(&mut self, bucket_id: u64) -> io::Result<Vec<(String, u64)>> {
+        if let Some(entries) = self.cache.get(bucket_id) {
+            return Ok(entries.clone());
+        }
+
+        let region_size = self.region_size();
+        let mut buffer = vec![0u8; region_size as usize];
+        self.file
+            .seek(SeekFrom::Start(HEADER_SIZE + bucket_id * region_size))?;
+        self.file.read_exact(&mut buffer)?;
+
+        let entries: Vec<(String, u64)> = bincode::deserialize(&buffer).unwrap_or_default();
+
+        self.cache.insert(bucket_id, entries.clone());
+        Ok(entries)
+    }
+
+    fn write_bucket(&mut self, bucket_id: u64, entries: &[(String, u64)]) -> io::Result<()> {
+        let region_size = self.region_size();
+        let bytes = bincode::serialize(&entries.to_vec())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        if bytes.len() as u64 > region_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "id index bucket entry too large for its fixed-size region",
+            ));
+        }
+
+        let mut buffer = vec![0u8; region_size as usize];
+        buffer[..bytes.len()].copy_from_slice(&bytes);
+
+        self.file
+            .seek(SeekFrom::Start(HEADER_SIZE + bucket_id * region_size))?;
+        self.file.write_all(&buffer)?;
+        self.cache.insert(bucket_id, entries.to_vec());
+        Ok(())
+    }
+
+    /// Doubles the bucket directory and redistributes every entry
+    /// across the new bucket count, amortizing the cost of growth over
+    /// the doubled capacity.
+    fn grow(&mut self) -> io::Result<()> {
+        let old_bucket_count = 1u64 << self.k;
+        let mut all_entries = Vec::new();
+        for bucket_id in 0..old_bucket_count {
+            all_entries.extend(self.read_bucket(bucket_id)?);
+        }
+
+        self.k += 1;
+        self.cache.clear();
+        self.write_header()?;
+
+        let new_bucket_count = 1u64 << self.k;
+        self.file
+            .set_len(HEADER_SIZE + new_bucket_count * self.region_size())?;
+
+        let mut grouped: HashMap<u64, Vec<(String, u64)>> = HashMap::new();
+        for (id, block) in all_entries {
+            let bucket_id = self.bucket_id(Self::hash_id(&id));
+            grouped.entry(bucket_id).or_default().push((id, block));
+        }
+        for bucket_id in 0..new_bucket_count {
+            let entries = grouped.remove(&bucket_id).unwrap_or_default();
+            self.write_bucket(bucket_id, &entries)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&mut self, id: &str) -> io::Result<Option<u64>> {
+        let bucket_id = self.bucket_id(Self::hash_id(id));
+        let entries = self.read_bucket(bucket_id)?;
+        Ok(entries
+            .iter()
+            .find(|(entry_id, _)| entry_id == id)
+            .map(|&(_, block)| block))
+    }
+
+    pub fn set(&mut self, id: &str, first_block: u64) -> io::Result<()> {
+        loop {
+            let bucket_id = self.bucket_id(Self::hash_id(id));
+            let mut entries = self.read_bucket(bucket_id)?;
+
+            if let Some(entry) = entries.iter_mut().find(|(entry_id, _)| entry_id == id) {
+                entry.1 = first_block;
+                return self.write_bucket(bucket_id, &entries);
+            }
+
+            if entries.len() >= self.max_probe_len {
+                self.grow()?;
+                continue;
+            }
+
+            entries.push((id.to_string(), first_block));
+            return self.write_bucket(bucket_id, &entries);
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) -> io::Result<Option<u64>> {
+        let bucket_id = self.bucket_id(Self::hash_id(id));
+        let mut entries = self.read_bucket(bucket_id)?;
+
+        match entries.iter().position(|(entry_id, _)| entry_id == id) {
+            Some(pos) => {
+                let (_, block) = entries.remove(pos);
+                self.write_bucket(bucket_id, &entries)?;
+                Ok(Some(block))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Every id currently recorded in the index, gathered by scanning
+    /// every bucket. Lets callers rebuild in-memory structures (e.g.
+    /// `TextIndex`/`SecondaryIndex`) derived from persisted documents
+    /// on open, since the index itself doesn't keep those around.
+    pub fn ids(&mut self) -> io::Result<Vec<String>> {
+        let bucket_count = 1u64 << self.k;
+        let mut ids = Vec::new();
+        for bucket_id in 0..bucket_count {
+            ids.extend(self.read_bucket(bucket_id)?.into_iter().map(|(id, _)| id));
+        }
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        temp_dir().join(format!("bucket_index_test_{name}_{}.idx", std::process::id()))
+    }
+
+    #[test]
+    fn set_get_remove_roundtrip() {
+        let path = temp_path("roundtrip");
+        let mut index = BucketIndex::open(&path, BucketIndexConfig::default()).unwrap();
+
+        index.set("doc-1", 42).unwrap();
+        assert_eq!(index.get("doc-1").unwrap(), Some(42));
+
+        assert_eq!(index.remove("doc-1").unwrap(), Some(42));
+        assert_eq!(index.get("doc-1").unwrap(), None);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn grows_the_directory_past_the_probe_length() {
+        let path = temp_path("grow");
+        let config = BucketIndexConfig {
+            initial_k: 1,
+            max_probe_len: 2,
+            cache_size: 4,
+        };
+        let mut index = BucketIndex::open(&path, config).unwrap();
+
+        for i in 0..100 {
+            index.set(&format!("doc-{i}"), i as u64).unwrap();
+        }
+        assert!(index.k > 1);
+
+        for i in 0..100 {
+            assert_eq!(index.get(&format!("doc-{i}")).unwrap(), Some(i as u64));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reopening_preserves_entries_and_directory_size() {
+        let path = temp_path("reopen");
+        let config = BucketIndexConfig {
+            initial_k: 1,
+            max_probe_len: 2,
+            cache_size: 4,
+        };
+        {
+            let mut index = BucketIndex::open(&path, config).unwrap();
+            for i in 0..20 {
+                index.set(&format!("doc-{i}"), i as u64).unwrap();
+            }
+        }
+
+        let mut reopened = BucketIndex::open(&path, BucketIndexConfig::default()).unwrap();
+        for i in 0..20 {
+            assert_eq!(reopened.get(&format!("doc-{i}")).unwrap(), Some(i as u64));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn distinguishes_ids_that_collide_into_the_same_bucket() {
+        let path = temp_path("collision");
+        // A single bucket forces every id into bucket 0, simulating a
+        // hash collision regardless of what `hash_id` actually returns.
+        let config = BucketIndexConfig {
+            initial_k: 0,
+            max_probe_len: 8,
+            cache_size: 4,
+        };
+        let mut index = BucketIndex::open(&path, config).unwrap();
+
+        index.set("alice", 1).unwrap();
+        index.set("bob", 2).unwrap();
+
+        assert_eq!(index.get("alice").unwrap(), Some(1));
+        assert_eq!(index.get("bob").unwrap(), Some(2));
+
+        index.remove("alice").unwrap();
+        assert_eq!(index.get("alice").unwrap(), None);
+        assert_eq!(index.get("bob").unwrap(), Some(2));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ids_lists_every_recorded_id() {
+        let path = temp_path("ids");
+        let mut index = BucketIndex::open(&path, BucketIndexConfig::default()).unwrap();
+
+        index.set("doc-1", 1).unwrap();
+        index.set("doc-2", 2).unwrap();
+        index.remove("doc-1").unwrap();
+
+        let mut ids = index.ids().unwrap();
+        ids.sort();
+        assert_eq!(ids, vec!["doc-2".to_string()]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
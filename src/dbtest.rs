@@ -19,8 +19,7 @@ mod tests {
 
     #[test]
     fn test_database_connection() {
-        let db_path = Path::new("test.bin");
-        let mut storage = StorageEngine::new(db_path).unwrap();
+        let mut storage = StorageEngine::new_in_memory().unwrap();
 
         let doc1 = create_test_document("Alice", 30, "New York");
         let doc2 = create_test_document("Bob", 25, "San Francisco");
@@ -54,7 +53,98 @@ mod tests {
         assert_eq!(retrieved_doc1.get("name"), doc1.get("name"));
         assert_eq!(retrieved_doc1.get("age"), doc1.get("age"));
         assert_eq!(retrieved_doc1.get("city"), doc1.get("city"));
+    }
+
+    /// Regression test for a free-list leak: a `Write` that's durably
+    /// logged to the WAL but never reaches its final checkpoint must
+    /// still keep its blocks out of the free list on replay, or a
+    /// later allocation can reuse them and silently clobber live data.
+    #[test]
+    fn recovers_a_write_interrupted_before_its_checkpoint() {
+        use crate::storage::{Block, BlockMetadata, DiskManager, Storage};
+        use crate::wal::WalEntry;
+
+        let db_path = Path::new("test_crash_recovery_write.bin");
+        let _ = std::fs::remove_file(db_path);
+        let _ = std::fs::remove_file("test_crash_recovery_write.bin.wal");
+        let _ = std::fs::remove_file("test_crash_recovery_write.bin.idx");
+
+        // A clean, fully-checkpointed baseline: write "a", then delete
+        // it, freeing its one block.
+        {
+            let mut storage = StorageEngine::new(db_path).unwrap();
+            storage.write("a", b"aaaa").unwrap();
+            storage.delete("a").unwrap();
+        }
+
+        // Simulate a crash: write "b" into the freed block, logging the
+        // WAL entry and allocating the block, but stopping short of the
+        // checkpoint that would persist the updated free list.
+        {
+            let mut manager = DiskManager::new(db_path).unwrap();
+            manager.load_free_blocks().unwrap();
+
+            let block_num = manager.allocate_block().unwrap();
+            manager
+                .append_wal(&WalEntry::Write {
+                    id: "b".to_string(),
+                    blocks: vec![block_num],
+                })
+                .unwrap();
+            manager
+                .write_block(
+                    block_num,
+                    &Block {
+                        metadata: BlockMetadata {
+                            id: "b".to_string(),
+                            size: 4,
+                            next_block: None,
+                        },
+                        data: b"bbbb".to_vec(),
+                    },
+                )
+                .unwrap();
+            manager.set_id("b", block_num).unwrap();
+            // No checkpoint_free_blocks()/checkpoint_wal() call: the
+            // superblock on disk still lists `block_num` as free.
+        }
+
+        // Reopening replays the WAL and must recognize `block_num` as
+        // live again before the free list is checkpointed.
+        let mut storage = StorageEngine::new(db_path).unwrap();
+        storage.write("c", b"cccc").unwrap();
+
+        assert_eq!(storage.read("b").unwrap().unwrap(), b"bbbb");
+        assert_eq!(storage.read("c").unwrap().unwrap(), b"cccc");
 
         std::fs::remove_file(db_path).expect("Failed to remove test database file");
+        let _ = std::fs::remove_file("test_crash_recovery_write.bin.wal");
+        let _ = std::fs::remove_file("test_crash_recovery_write.bin.idx");
+    }
+
+    #[test]
+    fn test_in_memory_storage_roundtrip() {
+        let mut storage = StorageEngine::new_in_memory().unwrap();
+
+        let doc = create_test_document("Dana", 28, "Austin");
+        storage
+            .write(
+                doc.id().to_string().as_str(),
+                &serde_json::to_vec(&doc).unwrap(),
+            )
+            .expect("Failed to write doc");
+
+        let retrieved = storage
+            .read(doc.id().to_string().as_str())
+            .expect("Failed to read doc")
+            .unwrap();
+        let retrieved: Document = serde_json::from_slice(&retrieved).unwrap();
+
+        assert_eq!(retrieved.get("name"), doc.get("name"));
+
+        storage
+            .delete(doc.id().to_string().as_str())
+            .expect("Failed to delete doc");
+        assert!(storage.read(doc.id().to_string().as_str()).unwrap().is_none());
     }
 }
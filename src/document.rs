@@ -90,6 +90,10 @@ impl Document {
     pub fn values(&self) -> impl Iterator<Item = &Value> {
         self.data.values()
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.data.iter()
+    }
 }
 
 impl Default for Document {
@@ -3,10 +3,17 @@ use std::path::Path;
 use document::{Document, Value};
 use storage::StorageEngine;
 
+pub mod bitmap;
+pub mod bucket_index;
 mod dbtest;
 pub mod document;
+pub mod memory_storage;
+pub mod migrate;
 pub mod query;
+pub mod secondary_index;
 pub mod storage;
+pub mod text;
+pub mod wal;
 
 pub fn create_test_document(name: &str, age: i64, city: &str) -> Document {
     let mut doc = Document::new();
@@ -17,6 +24,19 @@ pub fn create_test_document(name: &str, age: i64, city: &str) -> Document {
 }
 
 fn main() {
+    let mut args = std::env::args().skip(1);
+    if let Some("upgrade") = args.next().as_deref() {
+        let path = args
+            .next()
+            .expect("usage: rust-nosql-db upgrade <path>");
+        StorageEngine::upgrade(&path).expect("Failed to upgrade database");
+        println!(
+            "Upgraded {path} to format version {}",
+            storage::FORMAT_VERSION
+        );
+        return;
+    }
+
     let db_path = Path::new("test.bin");
     let mut storage = StorageEngine::new(db_path).unwrap();
 
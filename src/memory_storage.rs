@@ -0,0 +1,74 @@
+//! Ephemeral [`Storage`](crate::storage::Storage) backend that keeps
+//! every block in memory instead of a file. Useful for embedding the
+//! database without touching the filesystem, and for tests that would
+//! otherwise need to create and clean up temp files.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+use crate::storage::{Block, Storage};
+
+#[derive(Default)]
+pub struct MemoryStorage {
+    blocks: HashMap<u64, Block>,
+    free_blocks: VecDeque<u64>,
+    next_block: u64,
+    index: HashMap<String, u64>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn allocate_block(&mut self) -> io::Result<u64> {
+        if let Some(block_num) = self.free_blocks.pop_front() {
+            Ok(block_num)
+        } else {
+            let block_num = self.next_block;
+            self.next_block += 1;
+            Ok(block_num)
+        }
+    }
+
+    fn read_block(&mut self, block_num: u64) -> io::Result<Block> {
+        self.blocks
+            .get(&block_num)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "block not found"))
+    }
+
+    fn write_block(&mut self, block_num: u64, block: &Block) -> io::Result<()> {
+        self.blocks.insert(block_num, block.clone());
+        Ok(())
+    }
+
+    fn free_block(&mut self, block_num: u64) -> io::Result<()> {
+        self.free_blocks.push_back(block_num);
+        Ok(())
+    }
+
+    fn unfree_block(&mut self, block_num: u64) -> io::Result<()> {
+        self.free_blocks.retain(|&b| b != block_num);
+        Ok(())
+    }
+
+    fn get_id(&mut self, id: &str) -> io::Result<Option<u64>> {
+        Ok(self.index.get(id).copied())
+    }
+
+    fn set_id(&mut self, id: &str, first_block: u64) -> io::Result<()> {
+        self.index.insert(id.to_string(), first_block);
+        Ok(())
+    }
+
+    fn remove_id(&mut self, id: &str) -> io::Result<Option<u64>> {
+        Ok(self.index.remove(id))
+    }
+
+    fn ids(&mut self) -> io::Result<Vec<String>> {
+        Ok(self.index.keys().cloned().collect())
+    }
+}
@@ -0,0 +1,194 @@
+//! Sequential migration path for the on-disk superblock and block
+//! formats.
+//!
+//! Every historical superblock layout gets its own struct below, plus a
+//! `upgrade_vN_to_vN+1` function that rewrites its serialized bytes
+//! into the next version's layout. `UPGRADES` registers these in
+//! order; for a superblock without the current magic prefix,
+//! `read_superblock` runs every step in that order, each on the
+//! previous step's output (a step whose input doesn't parse leaves the
+//! bytes unchanged), and then deserializes the result as the current
+//! [`Superblock`]. The version registered beside each step is not
+//! checked against the bytes. Adding a new version means adding one more
+//! struct and appending one more entry to `UPGRADES` — existing steps
+//! never need to change.
+//!
+//! Both `read_superblock` and `read_block` detect the on-disk version
+//! from an explicit magic-number-and-version prefix, rather than
+//! guessing by trying to deserialize the current layout first — a
+//! buffer from an old layout could otherwise happen to parse as the
+//! current one and silently return garbage instead of migrating.
+
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{
+    Block, BlockMetadata, Superblock, BLOCK_FORMAT_VERSION, BLOCK_MAGIC, FORMAT_VERSION,
+    SUPERBLOCK_MAGIC,
+};
+
+/// The original on-disk superblock, written before format versioning
+/// existed: just the free list, with no version marker at all.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SuperblockV0 {
+    free_blocks: Vec<u64>,
+}
+
+fn upgrade_v0_to_v1(bytes: &[u8]) -> Option<Vec<u8>> {
+    let v0: SuperblockV0 = bincode::deserialize(bytes).ok()?;
+    let v1 = Superblock {
+        version: 1,
+        free_blocks: v0.free_blocks,
+    };
+    bincode::serialize(&v1).ok()
+}
+
+/// `(version migrated away from, upgrade function)`, in ascending
+/// order so each step can assume every earlier one has already run.
+const UPGRADES: &[(u32, fn(&[u8]) -> Option<Vec<u8>>)] = &[(0, upgrade_v0_to_v1)];
+
+/// Reads a superblock out of a raw block buffer, migrating it up to
+/// `FORMAT_VERSION` if it was written by an older build of the crate.
+/// A buffer that doesn't parse under any known version falls back to a
+/// fresh superblock at the current version. A freshly zeroed block
+/// parses as a pre-versioning superblock with an empty free list, which
+/// gives the same result.
+pub(crate) fn read_superblock(bytes: &[u8]) -> Superblock {
+    if bytes.len() >= 4 && u32::from_le_bytes(bytes[0..4].try_into().unwrap()) == SUPERBLOCK_MAGIC
+    {
+        if let Ok(current) = bincode::deserialize::<Superblock>(&bytes[4..]) {
+            if current.version == FORMAT_VERSION {
+                return current;
+            }
+        }
+    }
+
+    // No recognized magic prefix: the pre-versioning layout, which is
+    // just a bincode-encoded `SuperblockV0` starting at byte 0.
+    let mut buffer = bytes.to_vec();
+    for &(_from_version, upgrade) in UPGRADES {
+        if let Some(upgraded) = upgrade(&buffer) {
+            buffer = upgraded;
+        }
+    }
+
+    bincode::deserialize(&buffer).unwrap_or_default()
+}
+
+/// The original on-disk block layout, written before format versioning
+/// existed: no magic/version prefix at all, just the bincode-encoded
+/// metadata immediately followed by raw data.
+fn read_block_v0(bytes: &[u8]) -> io::Result<Block> {
+    let metadata: BlockMetadata =
+        bincode::deserialize(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let metadata_len = bincode::serialize(&metadata)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        .len();
+
+    Ok(Block {
+        metadata,
+        data: bytes[metadata_len..].to_vec(),
+    })
+}
+
+/// Reads a block out of a raw buffer, detecting its on-disk version
+/// the same way [`read_superblock`] does: an explicit magic-number
+/// prefix rather than a guess-by-deserializing. A buffer without the
+/// current magic-and-version prefix is read as the pre-versioning
+/// layout (see `read_block_v0`).
+pub(crate) fn read_block(bytes: &[u8]) -> io::Result<Block> {
+    if bytes.len() >= 8 && u32::from_le_bytes(bytes[0..4].try_into().unwrap()) == BLOCK_MAGIC {
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version == BLOCK_FORMAT_VERSION {
+            let metadata: BlockMetadata = bincode::deserialize(&bytes[8..])
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let metadata_len = bincode::serialize(&metadata)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+                .len();
+
+            return Ok(Block {
+                metadata,
+                data: bytes[8 + metadata_len..].to_vec(),
+            });
+        }
+    }
+
+    read_block_v0(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_an_unversioned_superblock() {
+        let legacy = SuperblockV0 {
+            free_blocks: vec![3, 7, 11],
+        };
+        let mut bytes = bincode::serialize(&legacy).unwrap();
+        bytes.resize(4096, 0);
+
+        let superblock = read_superblock(&bytes);
+        assert_eq!(superblock.version, FORMAT_VERSION);
+        assert_eq!(superblock.free_blocks, vec![3, 7, 11]);
+    }
+
+    #[test]
+    fn reads_a_current_superblock_unchanged() {
+        let current = Superblock {
+            version: FORMAT_VERSION,
+            free_blocks: vec![42],
+        };
+        let mut bytes = SUPERBLOCK_MAGIC.to_le_bytes().to_vec();
+        bytes.extend(bincode::serialize(&current).unwrap());
+        bytes.resize(4096, 0);
+
+        let superblock = read_superblock(&bytes);
+        assert_eq!(superblock.version, FORMAT_VERSION);
+        assert_eq!(superblock.free_blocks, vec![42]);
+    }
+
+    #[test]
+    fn falls_back_to_an_empty_superblock_for_garbage_bytes() {
+        let superblock = read_superblock(&[0u8; 4096]);
+        assert_eq!(superblock.version, FORMAT_VERSION);
+        assert!(superblock.free_blocks.is_empty());
+    }
+
+    #[test]
+    fn reads_a_current_block_unchanged() {
+        let metadata = BlockMetadata {
+            id: "doc-1".to_string(),
+            size: 5,
+            next_block: None,
+        };
+        let metadata_bytes = bincode::serialize(&metadata).unwrap();
+
+        let mut bytes = BLOCK_MAGIC.to_le_bytes().to_vec();
+        bytes.extend(BLOCK_FORMAT_VERSION.to_le_bytes());
+        bytes.extend(&metadata_bytes);
+        bytes.extend(b"hello");
+        bytes.resize(4096, 0);
+
+        let block = read_block(&bytes).unwrap();
+        assert_eq!(block.metadata.id, "doc-1");
+        assert_eq!(&block.data[..block.metadata.size], b"hello");
+    }
+
+    #[test]
+    fn migrates_a_block_written_before_format_versioning() {
+        let metadata = BlockMetadata {
+            id: "doc-1".to_string(),
+            size: 5,
+            next_block: None,
+        };
+        let mut bytes = bincode::serialize(&metadata).unwrap();
+        bytes.extend(b"hello");
+        bytes.resize(4096, 0);
+
+        let block = read_block(&bytes).unwrap();
+        assert_eq!(block.metadata.id, "doc-1");
+        assert_eq!(&block.data[..block.metadata.size], b"hello");
+    }
+}
@@ -1,5 +1,10 @@
+use crate::bitmap::RoaringBitmap;
 use crate::document::{Document, Value};
+use crate::secondary_index::SecondaryIndex;
+use crate::text::{self, TextIndex};
 use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::ops::Bound;
 
 #[derive(Debug, Clone)]
 pub enum Operator {
@@ -11,6 +16,11 @@ pub enum Operator {
     Lte,
     In,
     Nin,
+    /// Case-insensitive substring match.
+    Contains,
+    /// Full-text match: every token in the query string must appear
+    /// somewhere in the field's tokenized text.
+    Match,
 }
 
 #[derive(Debug, Clone)]
@@ -20,24 +30,84 @@ pub struct Condition {
     value: Value,
 }
 
+/// A node in a query's boolean expression tree. `Leaf` is a single
+/// field condition; `And`/`Or`/`Not` combine subtrees so queries can
+/// nest groups instead of being a single flat conjunction.
+#[derive(Debug, Clone)]
+pub enum QueryNode {
+    Leaf(Condition),
+    And(Vec<QueryNode>),
+    Or(Vec<QueryNode>),
+    Not(Box<QueryNode>),
+}
+
+impl QueryNode {
+    /// Builds a single leaf condition node.
+    pub fn condition(field: impl Into<String>, operator: Operator, value: Value) -> Self {
+        QueryNode::Leaf(Condition {
+            field: field.into(),
+            operator,
+            value,
+        })
+    }
+}
+
+/// Collects every leaf condition that's unconditionally required by
+/// `node`, i.e. reachable only through `And` nodes. Conditions nested
+/// under an `Or` or `Not` don't narrow the candidate set on their own,
+/// so callers that use this for index pre-filtering still need to
+/// re-check the full tree afterward.
+fn collect_and_leaves<'q>(node: &'q QueryNode, out: &mut Vec<&'q Condition>) {
+    match node {
+        QueryNode::Leaf(condition) => out.push(condition),
+        QueryNode::And(nodes) => nodes.iter().for_each(|n| collect_and_leaves(n, out)),
+        QueryNode::Or(_) | QueryNode::Not(_) => {}
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Query {
-    conditions: Vec<Condition>,
+    root: QueryNode,
 }
 
 impl Query {
     pub fn new() -> Self {
         Self {
-            conditions: Vec::new(),
+            root: QueryNode::And(Vec::new()),
         }
     }
 
+    /// Builds a query from an explicit `QueryNode` tree, for nested
+    /// AND/OR/NOT composition beyond the flat conjunction
+    /// `add_condition` builds.
+    pub fn from_node(root: QueryNode) -> Self {
+        Self { root }
+    }
+
+    /// Adds `field operator value` to the query's top-level AND group.
+    /// Kept so existing callers that only need a flat conjunction of
+    /// conditions don't have to build a `QueryNode` tree by hand.
     pub fn add_condition(&mut self, field: impl Into<String>, operator: Operator, value: Value) {
-        self.conditions.push(Condition {
-            field: field.into(),
-            operator,
-            value,
-        });
+        let leaf = QueryNode::condition(field, operator, value);
+        match &mut self.root {
+            QueryNode::And(nodes) => nodes.push(leaf),
+            _ => {
+                let previous_root = std::mem::replace(&mut self.root, QueryNode::And(Vec::new()));
+                self.root = QueryNode::And(vec![previous_root, leaf]);
+            }
+        }
+    }
+
+    /// Builder for an OR group of nodes, to compose with
+    /// [`Query::from_node`].
+    pub fn or(nodes: Vec<QueryNode>) -> QueryNode {
+        QueryNode::Or(nodes)
+    }
+
+    /// Builder for negating a node, to compose with
+    /// [`Query::from_node`].
+    pub fn not(node: QueryNode) -> QueryNode {
+        QueryNode::Not(Box::new(node))
     }
 }
 
@@ -51,14 +121,151 @@ impl QueryExecutor {
     ) -> Vec<&'a Document> {
         documents
             .iter()
-            .filter(|doc| self.matches_all_conditions(doc, &query.conditions))
+            .filter(|doc| self.evaluate(doc, &query.root))
             .collect()
     }
 
-    fn matches_all_conditions(&self, doc: &Document, conditions: &[Condition]) -> bool {
-        conditions
-            .iter()
-            .all(|condition| self.matches_condition(doc, condition))
+    /// Like [`execute`](Self::execute), but uses `text_index` to
+    /// narrow the candidate set down to documents matching every
+    /// top-level `Match` condition, then runs the linear scan of the
+    /// whole query over those candidates only, instead of scanning
+    /// every document.
+    pub fn execute_with_text_index<'a>(
+        &self,
+        query: &Query,
+        documents: &'a [Document],
+        text_index: &TextIndex,
+    ) -> Vec<&'a Document> {
+        let mut leaves = Vec::new();
+        collect_and_leaves(&query.root, &mut leaves);
+
+        let mut candidate_ids: Option<HashSet<String>> = None;
+
+        for condition in leaves {
+            if let (Operator::Match, Value::String(text)) = (&condition.operator, &condition.value)
+            {
+                // With no terms to look up, `evaluate` accepts any string
+                // value, so such a condition can't narrow the candidates.
+                if text::tokenize(text).is_empty() {
+                    continue;
+                }
+                let hits = text_index.search_all(text);
+                candidate_ids = Some(match candidate_ids {
+                    Some(existing) => existing.intersection(&hits).cloned().collect(),
+                    None => hits,
+                });
+            }
+        }
+
+        let scan: Box<dyn Iterator<Item = &Document>> = match &candidate_ids {
+            Some(ids) => Box::new(
+                documents
+                    .iter()
+                    .filter(move |doc| ids.contains(&doc.id().to_string())),
+            ),
+            None => Box::new(documents.iter()),
+        };
+
+        scan.filter(|doc| self.evaluate(doc, &query.root)).collect()
+    }
+
+    /// Like [`execute`](Self::execute), but plans the query against
+    /// `secondary_index` first: top-level equality/range conditions over
+    /// indexed fields narrow the candidate set by intersecting posting
+    /// lists (cheapest condition first), and the per-document scan then
+    /// checks the whole query over those candidates only.
+    pub fn execute_with_secondary_index<'a>(
+        &self,
+        query: &Query,
+        documents: &'a [Document],
+        secondary_index: &SecondaryIndex,
+    ) -> Vec<&'a Document> {
+        let mut leaves = Vec::new();
+        collect_and_leaves(&query.root, &mut leaves);
+
+        let mut indexed_hits: Vec<RoaringBitmap> = Vec::new();
+
+        for condition in leaves {
+            if !secondary_index.has_field(&condition.field) {
+                continue;
+            }
+
+            let hits = match &condition.operator {
+                Operator::Eq => secondary_index.equals_bitmap(&condition.field, &condition.value),
+                Operator::In => match &condition.value {
+                    Value::Array(values) => {
+                        let mut union = RoaringBitmap::new();
+                        let mut found_any = false;
+                        for value in values {
+                            if let Some(hits) =
+                                secondary_index.equals_bitmap(&condition.field, value)
+                            {
+                                found_any = true;
+                                union.or_inplace(&hits);
+                            }
+                        }
+                        found_any.then_some(union)
+                    }
+                    _ => None,
+                },
+                Operator::Gt => secondary_index.range_bitmap(
+                    &condition.field,
+                    Bound::Excluded(&condition.value),
+                    Bound::Unbounded,
+                ),
+                Operator::Gte => secondary_index.range_bitmap(
+                    &condition.field,
+                    Bound::Included(&condition.value),
+                    Bound::Unbounded,
+                ),
+                Operator::Lt => secondary_index.range_bitmap(
+                    &condition.field,
+                    Bound::Unbounded,
+                    Bound::Excluded(&condition.value),
+                ),
+                Operator::Lte => secondary_index.range_bitmap(
+                    &condition.field,
+                    Bound::Unbounded,
+                    Bound::Included(&condition.value),
+                ),
+                _ => None,
+            };
+
+            if let Some(hits) = hits {
+                indexed_hits.push(hits);
+            }
+        }
+
+        // Plan: intersect the cheapest (smallest) indexed condition
+        // first, merging containers pairwise via `RoaringBitmap::and`
+        // rather than resolving every condition to ids up front.
+        indexed_hits.sort_by_key(RoaringBitmap::len);
+        let candidate_bitmap = indexed_hits
+            .into_iter()
+            .reduce(|acc, next| acc.and(&next));
+        let candidate_ids = candidate_bitmap.map(|bitmap| secondary_index.resolve(&bitmap));
+
+        let scan: Box<dyn Iterator<Item = &Document>> = match &candidate_ids {
+            Some(ids) => Box::new(
+                documents
+                    .iter()
+                    .filter(move |doc| ids.contains(&doc.id().to_string())),
+            ),
+            None => Box::new(documents.iter()),
+        };
+
+        scan.filter(|doc| self.evaluate(doc, &query.root)).collect()
+    }
+
+    /// Recursively evaluates a `QueryNode` tree against `doc`, short
+    /// circuiting `And`/`Or` the same way `Iterator::all`/`any` do.
+    fn evaluate(&self, doc: &Document, node: &QueryNode) -> bool {
+        match node {
+            QueryNode::Leaf(condition) => self.matches_condition(doc, condition),
+            QueryNode::And(nodes) => nodes.iter().all(|node| self.evaluate(doc, node)),
+            QueryNode::Or(nodes) => nodes.iter().any(|node| self.evaluate(doc, node)),
+            QueryNode::Not(node) => !self.evaluate(doc, node),
+        }
     }
 
     fn matches_condition(&self, doc: &Document, condition: &Condition) -> bool {
@@ -94,6 +301,27 @@ impl QueryExecutor {
                         true
                     }
                 }
+                Operator::Contains => {
+                    if let (Value::String(haystack), Value::String(needle)) =
+                        (doc_value, &condition.value)
+                    {
+                        haystack.to_lowercase().contains(&needle.to_lowercase())
+                    } else {
+                        false
+                    }
+                }
+                Operator::Match => {
+                    if let (Value::String(haystack), Value::String(needle)) =
+                        (doc_value, &condition.value)
+                    {
+                        let doc_tokens: HashSet<String> = text::tokenize(haystack).into_iter().collect();
+                        text::tokenize(needle)
+                            .iter()
+                            .all(|token| doc_tokens.contains(token))
+                    } else {
+                        false
+                    }
+                }
             }
         } else {
             false
@@ -109,3 +337,79 @@ impl QueryExecutor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn document_with(city: &str, age: i64) -> Document {
+        let mut doc = Document::new();
+        doc.insert("city".to_string(), Value::String(city.to_string()));
+        doc.insert("age".to_string(), Value::Integer(age));
+        doc
+    }
+
+    #[test]
+    fn add_condition_builds_an_implicit_top_level_and() {
+        let mut query = Query::new();
+        query.add_condition("city", Operator::Eq, Value::String("NYC".to_string()));
+        query.add_condition("age", Operator::Gt, Value::Integer(20));
+
+        let documents = vec![document_with("NYC", 30), document_with("NYC", 10)];
+        let results = QueryExecutor.execute(&query, &documents);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].get("age"), Some(&Value::Integer(30)));
+    }
+
+    #[test]
+    fn or_matches_either_branch() {
+        let query = Query::from_node(Query::or(vec![
+            QueryNode::condition("city", Operator::Eq, Value::String("NYC".to_string())),
+            QueryNode::condition("city", Operator::Eq, Value::String("LA".to_string())),
+        ]));
+
+        let documents = vec![
+            document_with("NYC", 30),
+            document_with("LA", 40),
+            document_with("SF", 50),
+        ];
+        let results = QueryExecutor.execute(&query, &documents);
+
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn not_negates_a_subtree() {
+        let query = Query::from_node(Query::not(QueryNode::condition(
+            "city",
+            Operator::Eq,
+            Value::String("NYC".to_string()),
+        )));
+
+        let documents = vec![document_with("NYC", 30), document_with("LA", 40)];
+        let results = QueryExecutor.execute(&query, &documents);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].get("city"), Some(&Value::String("LA".to_string())));
+    }
+
+    #[test]
+    fn nested_and_or_not_compose() {
+        // city == "NYC" AND NOT (age < 18)
+        let query = Query::from_node(QueryNode::And(vec![
+            QueryNode::condition("city", Operator::Eq, Value::String("NYC".to_string())),
+            Query::not(QueryNode::condition("age", Operator::Lt, Value::Integer(18))),
+        ]));
+
+        let documents = vec![
+            document_with("NYC", 30),
+            document_with("NYC", 10),
+            document_with("LA", 30),
+        ];
+        let results = QueryExecutor.execute(&query, &documents);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].get("age"), Some(&Value::Integer(30)));
+    }
+}
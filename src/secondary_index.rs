@@ -0,0 +1,259 @@
+//! Maintained secondary indexes keyed by `(field, Value)`, so
+//! [`QueryExecutor`](crate::query::QueryExecutor) can answer equality and
+//! range conditions by intersecting/union-ing posting lists instead of
+//! scanning every document.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
+
+use crate::bitmap::RoaringBitmap;
+use crate::document::{Document, Value};
+
+/// Total-order, hashable stand-in for [`Value`], so it can key a
+/// `BTreeMap`/`HashMap` even though `Value` itself is neither `Ord`
+/// nor `Hash` (it embeds `f64`). Floats are ordered/hashed by a
+/// sign-adjusted bit pattern that sorts in numeric order, which is total
+/// but doesn't collapse `-0.0`/`0.0`; documents are not expected to rely
+/// on that distinction.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum IndexKey {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(u64),
+    String(String),
+    Date(i64),
+}
+
+impl IndexKey {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(IndexKey::Null),
+            Value::Boolean(b) => Some(IndexKey::Boolean(*b)),
+            Value::Integer(i) => Some(IndexKey::Integer(*i)),
+            Value::Float(f) => {
+                // Flip every bit of negatives and only the sign bit of
+                // the rest, so the `u64` order is the numeric order.
+                let bits = f.to_bits();
+                Some(IndexKey::Float(if bits >> 63 == 1 { !bits } else { bits | (1 << 63) }))
+            }
+            Value::String(s) => Some(IndexKey::String(s.clone())),
+            Value::Date(d) => Some(IndexKey::Date(d.timestamp_nanos_opt().unwrap_or_default())),
+            // Arrays/objects have no natural total order; leave them
+            // out of the index, the executor falls back to scanning.
+            Value::Array(_) | Value::Object(_) => None,
+        }
+    }
+}
+
+/// Per-field posting lists, keyed by value and ordered so range
+/// conditions can be answered by unioning a contiguous key range.
+#[derive(Debug, Default)]
+pub struct SecondaryIndex {
+    by_field: HashMap<String, BTreeMap<IndexKey, RoaringBitmap>>,
+    ordinal_of: HashMap<String, u32>,
+    id_of_ordinal: Vec<String>,
+    /// The `(field, key)` pairs currently indexed for each id, so
+    /// [`remove_document`](Self::remove_document) can drop exactly what
+    /// was indexed without the caller having to hand the original
+    /// document back.
+    indexed_keys: HashMap<String, Vec<(String, IndexKey)>>,
+}
+
+impl SecondaryIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn ordinal_for(&mut self, id: &str) -> u32 {
+        if let Some(&ordinal) = self.ordinal_of.get(id) {
+            return ordinal;
+        }
+        let ordinal = self.id_of_ordinal.len() as u32;
+        self.id_of_ordinal.push(id.to_string());
+        self.ordinal_of.insert(id.to_string(), ordinal);
+        ordinal
+    }
+
+    /// Indexes every indexable top-level field of `doc` under `id`,
+    /// replacing any previous entry for that id so re-indexing on
+    /// update is idempotent.
+    pub fn index_document(&mut self, id: &str, doc: &Document) {
+        self.remove_document(id);
+
+        let ordinal = self.ordinal_for(id);
+        let mut keys = Vec::new();
+        for (field, value) in doc.iter() {
+            if let Some(key) = IndexKey::from_value(value) {
+                self.by_field
+                    .entry(field.clone())
+                    .or_default()
+                    .entry(key.clone())
+                    .or_default()
+                    .insert(ordinal);
+                keys.push((field.clone(), key));
+            }
+        }
+        self.indexed_keys.insert(id.to_string(), keys);
+    }
+
+    /// Removes every entry previously indexed for `id`. A no-op if `id`
+    /// was never indexed (or was already removed).
+    pub fn remove_document(&mut self, id: &str) {
+        let Some(&ordinal) = self.ordinal_of.get(id) else {
+            return;
+        };
+        let Some(keys) = self.indexed_keys.remove(id) else {
+            return;
+        };
+        for (field, key) in keys {
+            if let Some(postings) = self.by_field.get_mut(&field) {
+                if let Some(bitmap) = postings.get_mut(&key) {
+                    bitmap.remove(ordinal);
+                    if bitmap.is_empty() {
+                        postings.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn has_field(&self, field: &str) -> bool {
+        self.by_field.contains_key(field)
+    }
+
+    /// Documents whose `field` equals `value`.
+    pub fn equals(&self, field: &str, value: &Value) -> Option<HashSet<String>> {
+        Some(self.resolve(&self.equals_bitmap(field, value)?))
+    }
+
+    /// Like [`equals`](Self::equals), but returns the raw posting
+    /// bitmap instead of resolving it to ids, so callers combining
+    /// several conditions can intersect/union the bitmaps directly
+    /// and only resolve once at the end.
+    pub fn equals_bitmap(&self, field: &str, value: &Value) -> Option<RoaringBitmap> {
+        let key = IndexKey::from_value(value)?;
+        Some(self.by_field.get(field)?.get(&key)?.clone())
+    }
+
+    /// Documents whose `field` falls within `(lower, upper)`.
+    pub fn range(
+        &self,
+        field: &str,
+        lower: Bound<&Value>,
+        upper: Bound<&Value>,
+    ) -> Option<HashSet<String>> {
+        Some(self.resolve(&self.range_bitmap(field, lower, upper)?))
+    }
+
+    /// Like [`range`](Self::range), but returns the raw union bitmap
+    /// instead of resolving it to ids — see [`equals_bitmap`](Self::equals_bitmap).
+    pub fn range_bitmap(
+        &self,
+        field: &str,
+        lower: Bound<&Value>,
+        upper: Bound<&Value>,
+    ) -> Option<RoaringBitmap> {
+        let postings = self.by_field.get(field)?;
+        let to_key_bound = |bound: Bound<&Value>| -> Option<Bound<IndexKey>> {
+            match bound {
+                Bound::Included(v) => IndexKey::from_value(v).map(Bound::Included),
+                Bound::Excluded(v) => IndexKey::from_value(v).map(Bound::Excluded),
+                Bound::Unbounded => Some(Bound::Unbounded),
+            }
+        };
+
+        let lower_key = to_key_bound(lower)?;
+        let upper_key = to_key_bound(upper)?;
+        // Keys of other value types sort before or after this type's keys,
+        // so an open bound would take them in; keep only the bound's type.
+        let kind = [&lower_key, &upper_key].into_iter().find_map(|bound| match bound {
+            Bound::Included(key) | Bound::Excluded(key) => Some(std::mem::discriminant(key)),
+            Bound::Unbounded => None,
+        });
+
+        let mut union = RoaringBitmap::new();
+        for (key, bitmap) in postings.range((lower_key, upper_key)) {
+            if kind.map_or(true, |kind| kind == std::mem::discriminant(key)) {
+                union.or_inplace(bitmap);
+            }
+        }
+        Some(union)
+    }
+
+    /// Resolves a bitmap of ordinals back to the document ids they
+    /// stand for. Public so callers that merge several conditions'
+    /// bitmaps (see `QueryExecutor::execute_with_secondary_index`) can
+    /// resolve once at the end instead of per-condition.
+    pub fn resolve(&self, bitmap: &RoaringBitmap) -> HashSet<String> {
+        bitmap
+            .iter()
+            .filter_map(|ordinal| self.id_of_ordinal.get(ordinal as usize).cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Value;
+
+    fn doc_with(field: &str, value: Value) -> Document {
+        let mut doc = Document::new();
+        doc.insert(field.to_string(), value);
+        doc
+    }
+
+    #[test]
+    fn equals_finds_matching_documents() {
+        let mut index = SecondaryIndex::new();
+        let doc1 = doc_with("city", Value::String("NY".into()));
+        let doc2 = doc_with("city", Value::String("SF".into()));
+        index.index_document("1", &doc1);
+        index.index_document("2", &doc2);
+
+        assert_eq!(
+            index.equals("city", &Value::String("NY".into())),
+            Some(["1".to_string()].into_iter().collect())
+        );
+    }
+
+    #[test]
+    fn range_unions_the_matching_key_range() {
+        let mut index = SecondaryIndex::new();
+        for (id, age) in [("1", 25), ("2", 30), ("3", 40)] {
+            index.index_document(id, &doc_with("age", Value::Integer(age)));
+        }
+
+        let hits = index
+            .range(
+                "age",
+                Bound::Included(&Value::Integer(30)),
+                Bound::Unbounded,
+            )
+            .unwrap();
+        assert_eq!(hits, ["2", "3"].into_iter().map(String::from).collect());
+    }
+
+    #[test]
+    fn remove_document_drops_its_postings() {
+        let mut index = SecondaryIndex::new();
+        let doc = doc_with("city", Value::String("NY".into()));
+        index.index_document("1", &doc);
+        index.remove_document("1");
+        assert!(index.equals("city", &Value::String("NY".into())).is_none());
+    }
+
+    #[test]
+    fn reindexing_replaces_the_previous_value() {
+        let mut index = SecondaryIndex::new();
+        index.index_document("1", &doc_with("city", Value::String("NY".into())));
+        index.index_document("1", &doc_with("city", Value::String("LA".into())));
+
+        assert!(index.equals("city", &Value::String("NY".into())).is_none());
+        assert_eq!(
+            index.equals("city", &Value::String("LA".into())),
+            Some(["1".to_string()].into_iter().collect())
+        );
+    }
+}
@@ -2,58 +2,263 @@ use std::{
     collections::{HashMap, VecDeque},
     fs::{File, OpenOptions},
     io::{self, Read, Seek, SeekFrom, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 use std::clone::Clone;
 
+use crate::bucket_index::{BucketIndex, BucketIndexConfig};
+use crate::document::Document;
+use crate::memory_storage::MemoryStorage;
+use crate::secondary_index::SecondaryIndex;
+use crate::text::TextIndex;
+use crate::wal::{WalEntry, WriteAheadLog};
+
 const BLOCK_SIZE: usize = 4096; // 4KB blocks
 const CACHE_SIZE: usize = 1000; // Number of blocks to cache in RAM
+const SUPERBLOCK_NUM: u64 = 0; // Block 0 is reserved for the superblock
+
+/// Current on-disk superblock layout version. Bump this whenever
+/// `Superblock`'s shape changes, and add the matching upgrade function
+/// to `migrate::UPGRADES` so existing databases keep opening.
+pub(crate) const FORMAT_VERSION: u32 = 1;
+
+/// Marks a superblock buffer as written by a version-aware build, so
+/// `migrate::read_superblock` can tell a current-format superblock
+/// apart from the pre-versioning layout by an explicit byte pattern
+/// instead of just trying to deserialize it and hoping for the best.
+pub(crate) const SUPERBLOCK_MAGIC: u32 = u32::from_be_bytes(*b"SBLK");
+
+/// Current on-disk block layout version. Bump this whenever
+/// `BlockMetadata`'s shape changes, and teach `migrate::read_block` to
+/// read the previous layout.
+pub(crate) const BLOCK_FORMAT_VERSION: u32 = 1;
+
+/// Marks a block buffer as written by a version-aware build, mirroring
+/// `SUPERBLOCK_MAGIC` — see `migrate::read_block`.
+pub(crate) const BLOCK_MAGIC: u32 = u32::from_be_bytes(*b"BLK1");
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct BlockMetadata {
-    id: String,
-    size: usize,
-    next_block: Option<u64>,
+pub(crate) struct BlockMetadata {
+    pub(crate) id: String,
+    pub(crate) size: usize,
+    pub(crate) next_block: Option<u64>,
 }
 
+/// A document's data, broken into `BLOCK_SIZE`-sized blocks on disk.
+/// The on-disk layout of each block is `[magic:4][version:4][bincode
+/// BlockMetadata][raw data]` (see `migrate::read_block`), so a future
+/// change to `BlockMetadata`'s shape can be detected and migrated
+/// without guessing from its serialized bytes alone.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct Block {
-    metadata: BlockMetadata,
-    data: Vec<u8>,
+pub struct Block {
+    pub(crate) metadata: BlockMetadata,
+    pub(crate) data: Vec<u8>,
+}
+
+/// Durable snapshot of a backend's free block list, persisted so a
+/// restart can pick the database back up without rescanning every
+/// block to rebuild it. The document-id index itself is no longer part
+/// of this snapshot — it's paged to disk separately by `BucketIndex`.
+///
+/// `version` identifies this layout on disk (see `FORMAT_VERSION`) so
+/// `migrate::read_superblock` can detect and upgrade superblocks
+/// written by older builds of the crate.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Superblock {
+    pub(crate) version: u32,
+    pub(crate) free_blocks: Vec<u64>,
+}
+
+impl Default for Superblock {
+    fn default() -> Self {
+        Superblock {
+            version: FORMAT_VERSION,
+            free_blocks: Vec::new(),
+        }
+    }
+}
+
+/// The storage primitives a `StorageEngine` needs: block allocation and
+/// I/O, plus lookup/mutation of the document-id index. Implementing
+/// this lets callers trade durability for speed — see `DiskManager` for
+/// the file-backed implementation and `MemoryStorage` for an ephemeral,
+/// filesystem-free one.
+pub trait Storage {
+    fn allocate_block(&mut self) -> io::Result<u64>;
+    fn read_block(&mut self, block_num: u64) -> io::Result<Block>;
+    fn write_block(&mut self, block_num: u64, block: &Block) -> io::Result<()>;
+    fn free_block(&mut self, block_num: u64) -> io::Result<()>;
+    /// Removes `block_num` from the free list if it's on it. A no-op if
+    /// it isn't. Used to reconcile the free list against WAL entries
+    /// replayed on open — a `Write` that allocated `block_num` may have
+    /// happened after the free list was last checkpointed, so the
+    /// checkpointed list can still (incorrectly) call it free.
+    fn unfree_block(&mut self, block_num: u64) -> io::Result<()>;
+
+    /// Looks up the first block of the document stored under `id`.
+    fn get_id(&mut self, id: &str) -> io::Result<Option<u64>>;
+    /// Records (or updates) the first block of the document stored
+    /// under `id`.
+    fn set_id(&mut self, id: &str, first_block: u64) -> io::Result<()>;
+    /// Removes `id` from the index, returning its first block if it
+    /// was present.
+    fn remove_id(&mut self, id: &str) -> io::Result<Option<u64>>;
+    /// Every id currently recorded in the index. Used to rebuild
+    /// in-memory structures derived from persisted documents (the text
+    /// and secondary indexes) when a `StorageEngine` is opened.
+    fn ids(&mut self) -> io::Result<Vec<String>>;
+
+    /// Backends with on-disk free-list persistence (see `DiskManager`)
+    /// can override these lifecycle hooks. The default no-op is correct
+    /// for ephemeral backends, which manage their free list entirely in
+    /// memory and have nothing to recover after a restart.
+    fn load_free_blocks(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    fn checkpoint_free_blocks(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Backends with crash-recovery support (see `DiskManager`) can
+    /// override these. The default no-op is correct for ephemeral
+    /// backends, which have nothing to recover after a restart.
+    fn append_wal(&mut self, _entry: &WalEntry) -> io::Result<()> {
+        Ok(())
+    }
+    fn replay_wal(&mut self) -> io::Result<Vec<WalEntry>> {
+        Ok(Vec::new())
+    }
+    fn checkpoint_wal(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
-struct DiskManager {
+/// File-backed [`Storage`] implementation: documents live in fixed-size
+/// blocks in a single data file, with block 0 reserved for the
+/// superblock and mutations logged to a sibling write-ahead log before
+/// they're applied, so a crash mid-write/delete can be replayed. The
+/// document-id index is paged to disk via a sibling `BucketIndex` file
+/// rather than held entirely in RAM.
+pub struct DiskManager {
     file: File,
     free_blocks: VecDeque<u64>,
+    wal: WriteAheadLog,
+    id_index: BucketIndex,
 }
 
-struct Cache {
-    blocks: HashMap<u64, Block>,
-    lru: VecDeque<u64>,
+/// Collects every `Value::String` leaf out of a JSON document so it can
+/// be handed to the tokenizer. Documents are stored as opaque bytes, so
+/// this walks the generic JSON shape rather than `crate::document::Value`
+/// directly, keeping the storage layer decoupled from the document model.
+fn collect_strings(value: &JsonValue, out: &mut String) {
+    match value {
+        JsonValue::String(s) => {
+            out.push_str(s);
+            out.push(' ');
+        }
+        JsonValue::Array(items) => items.iter().for_each(|item| collect_strings(item, out)),
+        JsonValue::Object(fields) => fields.values().for_each(|item| collect_strings(item, out)),
+        _ => {}
+    }
 }
 
-pub struct StorageEngine {
-    disk: DiskManager,
-    cache: Cache,
-    index: HashMap<String, u64>, // Document ID to first block number
+/// Follows a document's block chain from `first_block`, concatenating
+/// every block's live bytes. Shared by `StorageEngine::read` and the
+/// index rebuild in `with_storage`, which both need to walk the same
+/// chain before a `StorageEngine` necessarily exists yet.
+fn read_chain<S: Storage>(storage: &mut S, cache: &mut Cache, first_block: u64) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut current_block = first_block;
+
+    loop {
+        let block = if let Some(cached) = cache.get(current_block) {
+            cached.clone()
+        } else {
+            let disk_block = storage.read_block(current_block)?;
+            cache.insert(current_block, disk_block.clone());
+            disk_block
+        };
+
+        data.extend_from_slice(&block.data[..block.metadata.size]);
+
+        if let Some(next) = block.metadata.next_block {
+            current_block = next;
+        } else {
+            break;
+        }
+    }
+
+    Ok(data)
 }
 
 impl DiskManager {
-    fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::with_config(path, BucketIndexConfig::default())
+    }
+
+    /// Opens (or creates) a data file backed by an id index tuned with
+    /// `config`, so callers can trade lookup latency for memory/IO.
+    pub fn with_config<P: AsRef<Path>>(path: P, config: BucketIndexConfig) -> io::Result<Self> {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
-            .open(path)?;
+            .open(&path)?;
+
+        if file.metadata()?.len() == 0 {
+            file.set_len(BLOCK_SIZE as u64)?; // reserve block 0 for the superblock
+        }
+
+        let wal = WriteAheadLog::open(Self::wal_path(&path))?;
+        let id_index = BucketIndex::open(Self::idx_path(&path), config)?;
 
         Ok(DiskManager {
             file,
             free_blocks: VecDeque::new(),
+            wal,
+            id_index,
         })
     }
 
+    fn wal_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut wal_path = path.as_ref().as_os_str().to_owned();
+        wal_path.push(".wal");
+        PathBuf::from(wal_path)
+    }
+
+    fn idx_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut idx_path = path.as_ref().as_os_str().to_owned();
+        idx_path.push(".idx");
+        PathBuf::from(idx_path)
+    }
+
+    fn read_superblock(&mut self) -> io::Result<Superblock> {
+        let mut buffer = vec![0u8; BLOCK_SIZE];
+        self.file
+            .seek(SeekFrom::Start(SUPERBLOCK_NUM * BLOCK_SIZE as u64))?;
+        self.file.read_exact(&mut buffer)?;
+
+        Ok(crate::migrate::read_superblock(&buffer))
+    }
+
+    fn write_superblock(&mut self, superblock: &Superblock) -> io::Result<()> {
+        let mut buffer = vec![0u8; BLOCK_SIZE];
+        buffer[0..4].copy_from_slice(&SUPERBLOCK_MAGIC.to_le_bytes());
+        let bytes = bincode::serialize(superblock)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        buffer[4..4 + bytes.len()].copy_from_slice(&bytes);
+
+        self.file
+            .seek(SeekFrom::Start(SUPERBLOCK_NUM * BLOCK_SIZE as u64))?;
+        self.file.write_all(&buffer)?;
+        self.file.flush()
+    }
+}
+
+impl Storage for DiskManager {
     fn allocate_block(&mut self) -> io::Result<u64> {
         if let Some(block_num) = self.free_blocks.pop_front() {
             Ok(block_num)
@@ -70,23 +275,20 @@ impl DiskManager {
             .seek(SeekFrom::Start(block_num * BLOCK_SIZE as u64))?;
         self.file.read_exact(&mut buffer)?;
 
-        let metadata: BlockMetadata =
-            bincode::deserialize(&buffer[..std::mem::size_of::<BlockMetadata>()])
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-        Ok(Block {
-            metadata,
-            data: buffer[std::mem::size_of::<BlockMetadata>()..].to_vec(),
-        })
+        crate::migrate::read_block(&buffer)
     }
 
     fn write_block(&mut self, block_num: u64, block: &Block) -> io::Result<()> {
         let mut buffer = vec![0u8; BLOCK_SIZE];
+        buffer[0..4].copy_from_slice(&BLOCK_MAGIC.to_le_bytes());
+        buffer[4..8].copy_from_slice(&BLOCK_FORMAT_VERSION.to_le_bytes());
+
         let metadata_bytes = bincode::serialize(&block.metadata)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let header = 8;
 
-        buffer[..metadata_bytes.len()].copy_from_slice(&metadata_bytes);
-        buffer[metadata_bytes.len()..metadata_bytes.len() + block.data.len()]
+        buffer[header..header + metadata_bytes.len()].copy_from_slice(&metadata_bytes);
+        buffer[header + metadata_bytes.len()..header + metadata_bytes.len() + block.data.len()]
             .copy_from_slice(&block.data);
 
         self.file
@@ -99,6 +301,57 @@ impl DiskManager {
         self.free_blocks.push_back(block_num);
         Ok(())
     }
+
+    fn unfree_block(&mut self, block_num: u64) -> io::Result<()> {
+        self.free_blocks.retain(|&b| b != block_num);
+        Ok(())
+    }
+
+    fn get_id(&mut self, id: &str) -> io::Result<Option<u64>> {
+        self.id_index.get(id)
+    }
+
+    fn set_id(&mut self, id: &str, first_block: u64) -> io::Result<()> {
+        self.id_index.set(id, first_block)
+    }
+
+    fn remove_id(&mut self, id: &str) -> io::Result<Option<u64>> {
+        self.id_index.remove(id)
+    }
+
+    fn ids(&mut self) -> io::Result<Vec<String>> {
+        self.id_index.ids()
+    }
+
+    fn load_free_blocks(&mut self) -> io::Result<()> {
+        let superblock = self.read_superblock()?;
+        self.free_blocks = superblock.free_blocks.into();
+        Ok(())
+    }
+
+    fn checkpoint_free_blocks(&mut self) -> io::Result<()> {
+        self.write_superblock(&Superblock {
+            version: FORMAT_VERSION,
+            free_blocks: self.free_blocks.iter().copied().collect(),
+        })
+    }
+
+    fn append_wal(&mut self, entry: &WalEntry) -> io::Result<()> {
+        self.wal.append(entry)
+    }
+
+    fn replay_wal(&mut self) -> io::Result<Vec<WalEntry>> {
+        self.wal.replay()
+    }
+
+    fn checkpoint_wal(&mut self) -> io::Result<()> {
+        self.wal.checkpoint()
+    }
+}
+
+struct Cache {
+    blocks: HashMap<u64, Block>,
+    lru: VecDeque<u64>,
 }
 
 impl Cache {
@@ -128,98 +381,205 @@ impl Cache {
     }
 }
 
-impl StorageEngine {
+pub struct StorageEngine<S: Storage = DiskManager> {
+    storage: S,
+    cache: Cache,
+    text_index: TextIndex,
+    secondary_index: SecondaryIndex,
+}
+
+impl StorageEngine<DiskManager> {
     pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        Ok(StorageEngine {
-            disk: DiskManager::new(path)?,
-            cache: Cache::new(),
-            index: HashMap::new(),
-        })
+        Self::with_storage(DiskManager::new(path)?)
     }
 
-    pub fn write(&mut self, id: &str, data: &[u8]) -> io::Result<()> {
-        let mut remaining = data;
-        let mut prev_block_num = None;
-        let mut first_block_num = None;
-
-        while !remaining.is_empty() {
-            let block_num = self.disk.allocate_block()?;
-            if first_block_num.is_none() {
-                first_block_num = Some(block_num);
-            }
+    /// Like [`Self::new`], but tunes the on-disk id index with `config`
+    /// instead of its defaults.
+    pub fn with_config<P: AsRef<Path>>(path: P, config: BucketIndexConfig) -> io::Result<Self> {
+        Self::with_storage(DiskManager::with_config(path, config)?)
+    }
 
-            let chunk_size = remaining
-                .len()
-                .min(BLOCK_SIZE - std::mem::size_of::<BlockMetadata>());
-            let chunk = &remaining[..chunk_size];
-
-            let block = Block {
-                metadata: BlockMetadata {
-                    id: id.to_string(),
-                    size: chunk_size,
-                    next_block: None,
-                },
-                data: chunk.to_vec(),
-            };
+    /// Rewrites the superblock of the database at `path` into the
+    /// current on-disk format (see `FORMAT_VERSION`), migrating it
+    /// through `migrate::UPGRADES` first if it was written by an older
+    /// build of the crate. Safe to call on an already-current-format
+    /// database — it's then just a no-op rewrite. Opening a database
+    /// normally (`StorageEngine::new`) also migrates it implicitly, so
+    /// this is only needed when callers want the upgrade to happen as
+    /// its own explicit step, e.g. from a CLI command.
+    pub fn upgrade<P: AsRef<Path>>(path: P) -> io::Result<()> {
+        let mut manager = DiskManager::new(path)?;
+        manager.load_free_blocks()?;
+        manager.checkpoint_free_blocks()
+    }
+}
 
-            self.disk.write_block(block_num, &block)?;
-            self.cache.insert(block_num, block);
+impl StorageEngine<MemoryStorage> {
+    pub fn new_in_memory() -> io::Result<Self> {
+        Self::with_storage(MemoryStorage::new())
+    }
+}
 
-            if let Some(prev) = prev_block_num {
-                let mut prev_block = self.disk.read_block(prev)?;
-                prev_block.metadata.next_block = Some(block_num);
-                self.disk.write_block(prev, &prev_block)?;
-                self.cache.insert(prev, prev_block);
+impl<S: Storage> StorageEngine<S> {
+    /// Builds a `StorageEngine` on top of any `Storage` backend,
+    /// replaying its write-ahead log (a no-op for backends that don't
+    /// keep one) and checkpointing before the first mutation.
+    pub fn with_storage(mut storage: S) -> io::Result<Self> {
+        storage.load_free_blocks()?;
+
+        for entry in storage.replay_wal()? {
+            match entry {
+                WalEntry::Write { id, blocks } => {
+                    if let Some(&first) = blocks.first() {
+                        storage.set_id(&id, first)?;
+                    }
+                    // These blocks are live again (or newly allocated);
+                    // the free list loaded above may be stale and still
+                    // think some of them are free if this write happened
+                    // after the last checkpoint.
+                    for block in &blocks {
+                        storage.unfree_block(*block)?;
+                    }
+                }
+                WalEntry::Delete { id, blocks } => {
+                    storage.remove_id(&id)?;
+                    for block in blocks {
+                        storage.free_block(block)?;
+                    }
+                }
             }
-
-            prev_block_num = Some(block_num);
-            remaining = &remaining[chunk_size..];
         }
 
-        if let Some(first) = first_block_num {
-            self.index.insert(id.to_string(), first);
+        storage.checkpoint_free_blocks()?;
+        storage.checkpoint_wal()?;
+
+        let mut cache = Cache::new();
+        let mut text_index = TextIndex::new();
+        let mut secondary_index = SecondaryIndex::new();
+
+        // The text and secondary indexes aren't persisted themselves, so
+        // rebuild them from whatever documents survived recovery above.
+        for id in storage.ids()? {
+            let Some(first_block) = storage.get_id(&id)? else {
+                continue;
+            };
+            let data = read_chain(&mut storage, &mut cache, first_block)?;
+
+            if let Ok(json) = serde_json::from_slice::<JsonValue>(&data) {
+                let mut text = String::new();
+                collect_strings(&json, &mut text);
+                text_index.index_document(&id, &text);
+            }
+
+            if let Ok(doc) = serde_json::from_slice::<Document>(&data) {
+                secondary_index.index_document(&id, &doc);
+            }
         }
 
-        Ok(())
+        Ok(StorageEngine {
+            storage,
+            cache,
+            text_index,
+            secondary_index,
+        })
     }
 
-    pub fn read(&mut self, id: &str) -> io::Result<Option<Vec<u8>>> {
-        if let Some(&first_block) = self.index.get(id) {
-            let mut data = Vec::new();
-            let mut current_block = first_block;
+    /// Flushes the backend's free list and truncates its write-ahead
+    /// log, converging the two back to a single consistent snapshot.
+    fn checkpoint(&mut self) -> io::Result<()> {
+        self.storage.checkpoint_free_blocks()?;
+        self.storage.checkpoint_wal()
+    }
 
-            loop {
-                let block = if let Some(cached) = self.cache.get(current_block) {
-                    cached.clone()
-                } else {
-                    let disk_block = self.disk.read_block(current_block)?;
-                    self.cache.insert(current_block, disk_block.clone());
-                    disk_block
+    pub fn write(&mut self, id: &str, data: &[u8]) -> io::Result<()> {
+        // 8 bytes reserved for the block's magic-number-and-version
+        // header (see `write_block`), plus room for the bincode-encoded
+        // metadata that follows it. Every block carries the same id, and
+        // `Some(next_block)` is the largest encoding of the rest.
+        let metadata_len = bincode::serialize(&BlockMetadata {
+            id: id.to_string(),
+            size: 0,
+            next_block: Some(0),
+        })
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        .len();
+        let chunk_size = BLOCK_SIZE - 8 - metadata_len;
+        let chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+
+        if !chunks.is_empty() {
+            let blocks = chunks
+                .iter()
+                .map(|_| self.storage.allocate_block())
+                .collect::<io::Result<Vec<u64>>>()?;
+
+            // Log the mutation before touching any block content so a
+            // crash between here and the checkpoint below can be replayed.
+            self.storage.append_wal(&WalEntry::Write {
+                id: id.to_string(),
+                blocks: blocks.clone(),
+            })?;
+
+            for (i, chunk) in chunks.iter().enumerate() {
+                let block = Block {
+                    metadata: BlockMetadata {
+                        id: id.to_string(),
+                        size: chunk.len(),
+                        next_block: blocks.get(i + 1).copied(),
+                    },
+                    data: chunk.to_vec(),
                 };
 
-                data.extend_from_slice(&block.data[..block.metadata.size]);
-
-                if let Some(next) = block.metadata.next_block {
-                    current_block = next;
-                } else {
-                    break;
-                }
+                self.storage.write_block(blocks[i], &block)?;
+                self.cache.insert(blocks[i], block);
             }
 
-            Ok(Some(data))
+            self.storage.set_id(id, blocks[0])?;
+        }
+
+        if let Ok(json) = serde_json::from_slice::<JsonValue>(data) {
+            let mut text = String::new();
+            collect_strings(&json, &mut text);
+            self.text_index.index_document(id, &text);
+        }
+
+        if let Ok(doc) = serde_json::from_slice::<Document>(data) {
+            self.secondary_index.index_document(id, &doc);
+        }
+
+        self.checkpoint()
+    }
+
+    /// The inverted text index built up from every JSON string in the
+    /// documents written so far (their `Value::String` fields, and also
+    /// their ids, timestamps and dates), for accelerated `Match` queries.
+    pub fn text_index(&self) -> &TextIndex {
+        &self.text_index
+    }
+
+    /// The maintained `(field, Value)` posting lists used to accelerate
+    /// `QueryExecutor::execute_with_secondary_index`.
+    pub fn secondary_index(&self) -> &SecondaryIndex {
+        &self.secondary_index
+    }
+
+    pub fn read(&mut self, id: &str) -> io::Result<Option<Vec<u8>>> {
+        if let Some(first_block) = self.storage.get_id(id)? {
+            read_chain(&mut self.storage, &mut self.cache, first_block).map(Some)
         } else {
             Ok(None)
         }
     }
 
     pub fn delete(&mut self, id: &str) -> io::Result<()> {
-        if let Some(&first_block) = self.index.get(id) {
+        if let Some(first_block) = self.storage.get_id(id)? {
+            let mut blocks = Vec::new();
+            let mut data = Vec::new();
             let mut current_block = first_block;
 
             loop {
-                let block = self.disk.read_block(current_block)?;
-                self.disk.free_block(current_block)?;
-                self.cache.blocks.remove(&current_block);
+                let block = self.storage.read_block(current_block)?;
+                blocks.push(current_block);
+                data.extend_from_slice(&block.data[..block.metadata.size]);
 
                 if let Some(next) = block.metadata.next_block {
                     current_block = next;
@@ -228,7 +588,24 @@ impl StorageEngine {
                 }
             }
 
-            self.index.remove(id);
+            self.secondary_index.remove_document(id);
+
+            // Log the freed blocks before actually freeing them so a
+            // crash mid-delete can be replayed on the next open.
+            self.storage.append_wal(&WalEntry::Delete {
+                id: id.to_string(),
+                blocks: blocks.clone(),
+            })?;
+
+            for block in blocks {
+                self.storage.free_block(block)?;
+                self.cache.blocks.remove(&block);
+            }
+
+            self.storage.remove_id(id)?;
+            self.text_index.remove_document(id);
+
+            self.checkpoint()?;
         }
 
         Ok(())
@@ -0,0 +1,142 @@
+//! Tokenization and inverted-index text search.
+//!
+//! This module separates *analysis* (turning a string into a normalized
+//! stream of tokens) from the *index* (mapping each token back to the
+//! documents and positions it appears in), mirroring how a real search
+//! engine is structured.
+
+use std::collections::{HashMap, HashSet};
+
+/// Small stopword list. Not exhaustive, just enough to keep common
+/// filler words out of the posting lists.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "has", "he", "in", "is", "it",
+    "its", "of", "on", "that", "the", "to", "was", "were", "will", "with",
+];
+
+/// Strips the most common Latin diacritics by mapping accented
+/// characters down to their unaccented ASCII equivalent. This is a
+/// lightweight stand-in for a full unidecode table, covering the
+/// characters likely to show up in `Value::String` fields.
+fn strip_accents(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ñ' => 'n',
+        'ç' => 'c',
+        other => other,
+    }
+}
+
+/// Tokenizes `text` into lowercase, accent-stripped, alphanumeric
+/// tokens, splitting on any run of non-alphanumeric characters and
+/// dropping stopwords.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .chars()
+        .map(strip_accents)
+        .collect::<String>()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty() && !STOPWORDS.contains(token))
+        .map(|token| token.to_string())
+        .collect()
+}
+
+/// An inverted index over a single text field: token -> document id ->
+/// positions of that token within the tokenized field. Keeping
+/// positions (rather than just membership) leaves room for phrase
+/// queries later without changing the shape of the index.
+#[derive(Debug, Default, Clone)]
+pub struct TextIndex {
+    postings: HashMap<String, HashMap<String, Vec<u32>>>,
+}
+
+impl TextIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indexes `text` under `doc_id`, replacing any previous entry for
+    /// that document so re-indexing on update is idempotent.
+    pub fn index_document(&mut self, doc_id: &str, text: &str) {
+        self.remove_document(doc_id);
+        for (position, token) in tokenize(text).into_iter().enumerate() {
+            self.postings
+                .entry(token)
+                .or_default()
+                .entry(doc_id.to_string())
+                .or_default()
+                .push(position as u32);
+        }
+    }
+
+    /// Removes every posting for `doc_id` from the index.
+    pub fn remove_document(&mut self, doc_id: &str) {
+        for postings in self.postings.values_mut() {
+            postings.remove(doc_id);
+        }
+        self.postings.retain(|_, postings| !postings.is_empty());
+    }
+
+    fn posting_list(&self, token: &str) -> HashSet<&str> {
+        self.postings
+            .get(token)
+            .map(|docs| docs.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the ids of documents containing every token in `text`,
+    /// by intersecting the posting lists of the tokenized query terms.
+    pub fn search_all(&self, text: &str) -> HashSet<String> {
+        let mut tokens = tokenize(text).into_iter();
+        let Some(first) = tokens.next() else {
+            return HashSet::new();
+        };
+
+        let mut result = self.posting_list(&first);
+        for token in tokens {
+            let next = self.posting_list(&token);
+            result.retain(|doc_id| next.contains(doc_id));
+            if result.is_empty() {
+                break;
+            }
+        }
+        result.into_iter().map(str::to_string).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenize_lowercases_splits_and_drops_stopwords() {
+        assert_eq!(
+            tokenize("Rust Engineer, from Montréal!"),
+            vec!["rust", "engineer", "montreal"]
+        );
+    }
+
+    #[test]
+    fn search_all_intersects_posting_lists() {
+        let mut index = TextIndex::new();
+        index.index_document("1", "rust engineer and systems programmer");
+        index.index_document("2", "rust enthusiast and hobby baker");
+        index.index_document("3", "java engineer");
+
+        let hits = index.search_all("rust engineer");
+        assert_eq!(hits, ["1"].into_iter().map(String::from).collect());
+    }
+
+    #[test]
+    fn remove_document_clears_its_postings() {
+        let mut index = TextIndex::new();
+        index.index_document("1", "rust engineer");
+        index.remove_document("1");
+        assert!(index.search_all("rust engineer").is_empty());
+    }
+}